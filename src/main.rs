@@ -1,108 +1,391 @@
 use clap::Parser;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
 use std::collections::BTreeMap;
 use std::error::Error;
 use std::ffi::OsString;
 use std::fs::create_dir_all;
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 
 /// Simple program to update target photo collection to have a folder structure
-/// of the source photo collection.
+/// of the source photo collection(s).
 /// The program tries to avoid copying files and moves files, which match in
 /// file name and size.
-/// The source collection is never modified.
+/// Such files are only moved once their contents also hash equal.
+/// The source collections are never modified.
 /// The files in the target collection are never deleted, but may be moved to
 /// new location to match the source structure.
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Source collection
-    source_directory: PathBuf,
+    /// Source collection(s). When -t/--target-directory is not given, the
+    /// last path here is used as the target instead, following `cp SOURCE...
+    /// DIRECTORY`'s legacy two-argument form. Not required with --resume,
+    /// which only needs the target directory.
+    paths: Vec<PathBuf>,
 
-    /// Target collection.
-    target_directory: PathBuf,
+    /// Target collection. Defaults to the last path in the positional list
+    /// when not given.
+    #[arg(short = 't', long = "target-directory")]
+    target_directory: Option<PathBuf>,
 
     /// Don't do anything, just list the actions.
     #[arg(short, long)]
     dry_run: bool,
+
+    /// Hash every candidate file up front instead of only hashing files
+    /// which collide on size and name. Slower; files are still only compared
+    /// against others sharing their size and name, so the plan is the same.
+    #[arg(long, visible_alias = "hash")]
+    verify: bool,
+
+    /// Resume a previously interrupted run from its journal instead of
+    /// analyzing the directories and planning a new one.
+    #[arg(long)]
+    resume: bool,
+
+    /// Open the computed plan in $EDITOR before running it, letting lines
+    /// be deleted or targets redirected.
+    #[arg(long, visible_alias = "edit")]
+    interactive: bool,
+
+    /// Number of worker threads used to read file metadata and hash files.
+    /// Defaults to the number of available CPUs.
+    #[arg(short, long)]
+    jobs: Option<usize>,
+
+    /// Only consider files whose relative path matches this glob pattern
+    /// (e.g. `*.jpg`). Repeatable; a file is included if it matches any of
+    /// them. When no --include is given, every file is a candidate.
+    #[arg(long = "include")]
+    include: Vec<String>,
+
+    /// Skip files whose relative path matches this glob pattern, even if
+    /// they also match --include. Repeatable.
+    #[arg(long = "exclude")]
+    exclude: Vec<String>,
+}
+
+/// Compiled --include/--exclude glob patterns, matched against each file's
+/// relative path. --exclude always takes precedence over --include.
+struct Filters {
+    include: Vec<glob::Pattern>,
+    exclude: Vec<glob::Pattern>,
+}
+
+impl Filters {
+    fn compile(include: &[String], exclude: &[String]) -> Result<Filters, String> {
+        let compile_all = |patterns: &[String]| -> Result<Vec<glob::Pattern>, String> {
+            patterns
+                .iter()
+                .map(|pattern| {
+                    glob::Pattern::new(pattern)
+                        .or(Err(format!("Invalid glob pattern \"{}\".", pattern)))
+                })
+                .collect()
+        };
+        Ok(Filters {
+            include: compile_all(include)?,
+            exclude: compile_all(exclude)?,
+        })
+    }
+
+    fn matches(&self, relative_path: &Path) -> bool {
+        let relative_path = relative_path.to_string_lossy();
+        if self.exclude.iter().any(|pattern| pattern.matches(&relative_path)) {
+            return false;
+        }
+        self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|pattern| pattern.matches(&relative_path))
+    }
+}
+
+/// Splits `Args` into the source directories to read from and the single
+/// target directory to synchronize into, supporting both `SOURCE... -t
+/// TARGET` and the legacy `SOURCE TARGET` form. Requires at least one
+/// source directory either way; use `resolve_target_directory` instead when
+/// resuming, since a resumed run never reads the sources.
+fn resolve_directories(
+    mut paths: Vec<PathBuf>,
+    target_directory: Option<PathBuf>,
+) -> Result<(Vec<PathBuf>, PathBuf), String> {
+    match target_directory {
+        Some(target) => {
+            if paths.is_empty() {
+                return Err("Expected at least one SOURCE directory.".to_string());
+            }
+            Ok((paths, target))
+        }
+        None => {
+            if paths.len() < 2 {
+                return Err(
+                    "Expected SOURCE... -t TARGET, or the legacy SOURCE TARGET form.".to_string(),
+                );
+            }
+            let target = paths.pop().ok_or("No target directory given.")?;
+            Ok((paths, target))
+        }
+    }
+}
+
+/// Resolves just the target directory to resume into, without requiring any
+/// source directories: `--resume` only needs to find the journal that was
+/// already written into the target, either via -t/--target-directory or,
+/// for symmetry with the legacy form, the one positional path given.
+fn resolve_target_directory(
+    paths: Vec<PathBuf>,
+    target_directory: Option<PathBuf>,
+) -> Result<PathBuf, String> {
+    match target_directory {
+        Some(target) => Ok(target),
+        None => paths.into_iter().next_back().ok_or(
+            "--resume needs -t/--target-directory, or the target directory as a positional path."
+                .to_string(),
+        ),
+    }
+}
+
+/// Content digest used to tell apart files which merely share a size and
+/// name. Currently a BLAKE3 hash of the full file contents.
+type Hash = [u8; 32];
+
+fn hash_file(path: &Path) -> Result<Hash, String> {
+    let mut file = std::fs::File::open(path).or(Err(format!(
+        "Failed to open \"{}\" for hashing.",
+        path.to_string_lossy()
+    )))?;
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).or(Err(format!(
+            "Failed to read \"{}\" while hashing.",
+            path.to_string_lossy()
+        )))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(*hasher.finalize().as_bytes())
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
 struct SizeAndName {
     size: u64,
     name: OsString,
 }
 
+/// A candidate file found during analysis. `root` is the collection this
+/// file was found under, so several source collections can be merged into
+/// one logical structure while still knowing where each file physically
+/// lives. `hash` is computed lazily: it stays `None` until something
+/// actually needs to compare this file's content against another one.
+struct FileCandidate {
+    root: PathBuf,
+    path: PathBuf,
+    hash: RefCell<Option<Hash>>,
+}
+
+impl FileCandidate {
+    fn new(root: PathBuf, path: PathBuf) -> FileCandidate {
+        FileCandidate {
+            root,
+            path,
+            hash: RefCell::new(None),
+        }
+    }
+
+    fn full_path(&self) -> PathBuf {
+        self.root.join(&self.path)
+    }
+
+    /// Returns the content hash of this file, computing and caching it on
+    /// first use.
+    fn hash(&self) -> Result<Hash, String> {
+        if let Some(hash) = *self.hash.borrow() {
+            return Ok(hash);
+        }
+        let hash = hash_file(&self.full_path())?;
+        *self.hash.borrow_mut() = Some(hash);
+        Ok(hash)
+    }
+}
+
 struct AnalyzedDirectory {
-    map: BTreeMap<SizeAndName, Vec<PathBuf>>,
+    map: BTreeMap<SizeAndName, Vec<FileCandidate>>,
 }
 
-fn analyze_sub_directory(
+/// Reads the `(size, name)` key and relative path of a single walked file,
+/// or `None` if it is filtered out by `filters`. Split out of
+/// `analyze_directory` so it can run on a rayon worker thread per entry.
+fn analyze_file(
     path: &Path,
-    base_path: &Path,
-    result: &mut AnalyzedDirectory,
-) -> Result<(), String> {
-    for entry in path.read_dir().or(Err(format!(
-        "Failed to read dir {}",
+    root: &Path,
+    filters: &Filters,
+) -> Result<Option<(SizeAndName, FileCandidate)>, String> {
+    let relative_path: &Path = path.strip_prefix(root).or(Err(format!(
+        "Prefix {} not in path {}.",
+        root.to_string_lossy(),
         path.to_string_lossy()
-    )))? {
-        let entry = entry.or(Err(format!(
-            "Faulty entry in dir {}",
+    )))?;
+    if !filters.matches(relative_path) {
+        return Ok(None);
+    }
+
+    let size = path
+        .metadata()
+        .or(Err(format!(
+            "Could not get metadata for {}",
             path.to_string_lossy()
-        )))?;
-        let path = &entry.path();
-        if path.is_dir() {
-            analyze_sub_directory(path, base_path, result)?;
-        } else if path.is_file() {
-            let size = path
-                .metadata()
+        )))?
+        .len();
+    let name = path
+        .file_name()
+        .ok_or(format!(
+            "Path {} did not end with a file name.",
+            path.to_string_lossy()
+        ))?
+        .to_owned();
+    Ok(Some((
+        SizeAndName { size, name },
+        FileCandidate::new(root.to_path_buf(), relative_path.to_path_buf()),
+    )))
+}
+
+/// Walks `root`, then reads the metadata of and hashes (when `verify` is set)
+/// its files using a rayon thread pool capped at `jobs` threads. Each file
+/// yields its own `BTreeMap`, which are then merged; the `Vec<FileCandidate>` under each
+/// key is sorted afterwards so the merged result does not depend on
+/// scheduling order. Files rejected by `filters` never enter the result.
+fn analyze_directory(
+    root: &Path,
+    verify: bool,
+    jobs: usize,
+    filters: &Filters,
+) -> Result<AnalyzedDirectory, String> {
+    let entries: Vec<PathBuf> = walkdir::WalkDir::new(root)
+        .into_iter()
+        .map(|entry| {
+            entry
                 .or(Err(format!(
-                    "Could not get metadata for {}",
-                    path.to_string_lossy()
-                )))?
-                .len();
-            let name = path
-                .file_name()
-                .ok_or(format!(
-                    "Path {} did not end with a file name.",
-                    path.to_string_lossy()
-                ))?
-                .to_owned();
-            let key = SizeAndName { size, name };
-            let entry: &mut Vec<PathBuf> = result.map.entry(key).or_insert(Vec::new());
-            let relative_path: &Path = path.strip_prefix(base_path).or(Err(format!(
-                "Prefix {} not in path {}.",
-                base_path.to_string_lossy(),
-                path.to_string_lossy()
-            )))?;
-            entry.push(relative_path.to_path_buf());
+                    "Faulty entry while walking {}",
+                    root.to_string_lossy()
+                )))
+                .map(|entry| entry.into_path())
+        })
+        .collect::<Result<Vec<PathBuf>, String>>()?
+        .into_iter()
+        .filter(|entry| entry.is_file())
+        .collect();
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs)
+        .build()
+        .or(Err("Failed to build the worker thread pool.".to_string()))?;
+
+    let partials: Vec<BTreeMap<SizeAndName, Vec<FileCandidate>>> = pool.install(|| {
+        entries
+            .par_iter()
+            .map(|entry| -> Result<BTreeMap<SizeAndName, Vec<FileCandidate>>, String> {
+                let mut partial: BTreeMap<SizeAndName, Vec<FileCandidate>> = BTreeMap::new();
+                if let Some((key, candidate)) = analyze_file(entry, root, filters)? {
+                    if verify {
+                        candidate.hash()?;
+                    }
+                    partial.entry(key).or_default().push(candidate);
+                }
+                Ok(partial)
+            })
+            .collect::<Result<Vec<_>, String>>()
+    })?;
+
+    let mut map: BTreeMap<SizeAndName, Vec<FileCandidate>> = BTreeMap::new();
+    for partial in partials {
+        for (key, mut candidates) in partial {
+            map.entry(key).or_default().append(&mut candidates);
         }
     }
-    Ok(())
+    for candidates in map.values_mut() {
+        candidates.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+
+    Ok(AnalyzedDirectory { map })
 }
 
-fn analyze_directory(path: &Path) -> Result<AnalyzedDirectory, String> {
-    let mut result = AnalyzedDirectory {
-        map: BTreeMap::new(),
-    };
-    analyze_sub_directory(path, path, &mut result)?;
-    Ok(result)
+/// Merges several analyzed source collections into one logical source. Two
+/// collections are allowed to place the same photo at the same relative
+/// path: if the content actually matches, only one copy is kept; if it
+/// differs, that is a real conflict and merging fails.
+///
+/// Same-relative-path candidates are compared across *all* of them, not
+/// only the ones which happen to also collide on `SizeAndName`: two
+/// different photos placed at the same relative path by different sources
+/// are a conflict regardless of whether they also happen to be the same
+/// size.
+fn merge_sources(per_source: Vec<AnalyzedDirectory>) -> Result<AnalyzedDirectory, String> {
+    let mut flat: Vec<(SizeAndName, FileCandidate)> = Vec::new();
+    for analyzed in per_source {
+        for (key, candidates) in analyzed.map {
+            for candidate in candidates {
+                flat.push((key.clone(), candidate));
+            }
+        }
+    }
+
+    let mut kept: Vec<(SizeAndName, FileCandidate)> = Vec::new();
+    for (key, candidate) in flat {
+        let mut same_as_kept = false;
+        for (_, existing) in &kept {
+            if existing.path == candidate.path && existing.root != candidate.root {
+                if existing.hash()? != candidate.hash()? {
+                    return Err(format!(
+                        "Conflict: \"{}\" has different content in \"{}\" and \"{}\".",
+                        candidate.path.to_string_lossy(),
+                        existing.root.to_string_lossy(),
+                        candidate.root.to_string_lossy()
+                    ));
+                }
+                same_as_kept = true;
+                break;
+            }
+        }
+        if !same_as_kept {
+            kept.push((key, candidate));
+        }
+    }
+
+    let mut map: BTreeMap<SizeAndName, Vec<FileCandidate>> = BTreeMap::new();
+    for (key, candidate) in kept {
+        map.entry(key).or_default().push(candidate);
+    }
+
+    Ok(AnalyzedDirectory { map })
 }
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 struct Copy {
     source: PathBuf,
     target: PathBuf,
 }
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 struct Move {
     source: PathBuf,
     target: PathBuf,
 }
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 struct RemoveDuplicate {
     duplicate: PathBuf,
     original: PathBuf,
 }
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 enum Operation {
     Copy(Copy),
     Move(Move),
@@ -112,21 +395,40 @@ enum Operation {
 fn display_analyzed_directory(analyzed_directory: &AnalyzedDirectory) {
     for (key, value) in &analyzed_directory.map {
         println!("size : {}, name : {}", key.size, key.name.to_string_lossy());
-        for path in value {
-            println!("    path : {}", path.to_string_lossy());
+        for candidate in value {
+            println!("    path : {}", candidate.path.to_string_lossy());
         }
     }
 }
 
+/// Candidates sharing a `SizeAndName` key, grouped by content hash; `None`
+/// is used for the single-candidate case where nothing needed hashing.
+type HashGroups<'a> = Vec<(Option<Hash>, Vec<&'a FileCandidate>)>;
+
+/// Groups a set of candidates sharing a `SizeAndName` key by content hash.
+/// A key with a single candidate is never hashed, since there is nothing to
+/// tell it apart from.
+fn group_by_hash(candidates: &[FileCandidate]) -> Result<HashGroups<'_>, String> {
+    if candidates.len() <= 1 {
+        return Ok(vec![(None, candidates.iter().collect())]);
+    }
+    let mut by_hash: BTreeMap<Hash, Vec<&FileCandidate>> = BTreeMap::new();
+    for candidate in candidates {
+        let hash = candidate.hash()?;
+        by_hash.entry(hash).or_default().push(candidate);
+    }
+    Ok(by_hash.into_iter().map(|(h, c)| (Some(h), c)).collect())
+}
+
 fn get_duplicates(analyzed_directory: &AnalyzedDirectory) -> Result<Vec<Vec<&Path>>, String> {
     let mut result = Vec::new();
-    for (_, value) in &analyzed_directory.map {
-        if value.len() > 1 {
-            let mut paths = Vec::new();
-            for path in value {
-                paths.push(path.as_path());
+    for candidates in analyzed_directory.map.values() {
+        if candidates.len() > 1 {
+            for (_, group) in group_by_hash(candidates)? {
+                if group.len() > 1 {
+                    result.push(group.into_iter().map(|c| c.path.as_path()).collect());
+                }
             }
-            result.push(paths);
         }
     }
     Ok(result)
@@ -148,34 +450,67 @@ fn sync(
     target_directory: &AnalyzedDirectory,
 ) -> Result<Vec<Operation>, String> {
     let mut result = Vec::new();
-    for (key, value) in &source_directory.map {
-        let source_path = value.first().ok_or("no path for source")?;
-        // let SizeAndName{size, name} = key;
-        let target_entry = target_directory.map.get(&key);
-        match target_entry {
-            Some(target_paths) => {
-                let mut chosen_target_path: &PathBuf = source_path;
-                if !target_paths.contains(&source_path) {
-                    chosen_target_path =
-                        target_paths.first().ok_or("empty list of target paths")?;
-                    result.push(Operation::Move(Move {
-                        source: chosen_target_path.clone(),
-                        target: source_path.clone(),
-                    }));
+    for (key, source_candidates) in &source_directory.map {
+        let source_groups = group_by_hash(source_candidates)?;
+        let target_candidates = target_directory.map.get(key);
+
+        for (source_hash, source_group) in source_groups {
+            let source_candidate = *source_group.first().ok_or("no path for source")?;
+            let source_path = &source_candidate.path;
+
+            // Only hash the target candidates once we know source and
+            // target actually collide on size and name; this keeps the
+            // common, non-colliding case free of hashing.
+            let matching_target_paths: Option<Vec<&PathBuf>> = match target_candidates {
+                Some(candidates) => {
+                    let source_hash = match source_hash {
+                        Some(hash) => hash,
+                        None => source_candidate.hash()?,
+                    };
+                    let mut matches = Vec::new();
+                    for candidate in candidates {
+                        if candidate.hash()? == source_hash {
+                            matches.push(&candidate.path);
+                        }
+                    }
+                    if matches.is_empty() {
+                        None
+                    } else {
+                        Some(matches)
+                    }
                 }
-                for target_path in target_paths.iter() {
-                    if target_path != chosen_target_path {
-                        result.push(Operation::RemoveDuplicate(RemoveDuplicate {
-                            duplicate: target_path.clone(),
-                            original: chosen_target_path.clone(),
+                None => None,
+            };
+
+            match matching_target_paths {
+                Some(target_paths) => {
+                    let mut chosen_target_path: &PathBuf = source_path;
+                    if !target_paths.contains(&source_path) {
+                        chosen_target_path =
+                            target_paths.first().copied().ok_or("empty list of target paths")?;
+                        result.push(Operation::Move(Move {
+                            source: chosen_target_path.clone(),
+                            target: source_path.clone(),
                         }));
                     }
+                    for target_path in target_paths.iter() {
+                        if *target_path != chosen_target_path {
+                            // `original` must be the file's final location, not its
+                            // pre-move one: when a move is also queued for this
+                            // group, it already relocated chosen_target_path to
+                            // source_path by the time this step runs.
+                            result.push(Operation::RemoveDuplicate(RemoveDuplicate {
+                                duplicate: (*target_path).clone(),
+                                original: source_path.clone(),
+                            }));
+                        }
+                    }
                 }
+                None => result.push(Operation::Copy(Copy {
+                    source: source_candidate.full_path(),
+                    target: source_path.clone(),
+                })),
             }
-            None => result.push(Operation::Copy(Copy {
-                source: source_path.clone(),
-                target: source_path.clone(),
-            })),
         }
     }
 
@@ -211,6 +546,165 @@ fn print_operation(operation: &Operation) {
     }
 }
 
+/// Renders a plan as a human-editable buffer, one operation per line, in
+/// the format expected by `parse_operations`.
+fn format_operations(operations: &[Operation]) -> String {
+    let mut buffer = String::new();
+    for operation in operations {
+        match operation {
+            Operation::Copy(Copy { source, target }) => {
+                buffer.push_str(&format!(
+                    "copy {} -> {}\n",
+                    source.to_string_lossy(),
+                    target.to_string_lossy()
+                ));
+            }
+            Operation::Move(Move { source, target }) => {
+                buffer.push_str(&format!(
+                    "move {} -> {}\n",
+                    source.to_string_lossy(),
+                    target.to_string_lossy()
+                ));
+            }
+            Operation::RemoveDuplicate(RemoveDuplicate {
+                duplicate,
+                original,
+            }) => {
+                buffer.push_str(&format!(
+                    "remove-duplicate {} -> {}\n",
+                    duplicate.to_string_lossy(),
+                    original.to_string_lossy()
+                ));
+            }
+        }
+    }
+    buffer
+}
+
+/// Parses a buffer produced (and possibly edited) from `format_operations`
+/// back into a plan. Blank lines are ignored, so deleting a line simply
+/// drops that operation from the plan.
+fn parse_operations(text: &str) -> Result<Vec<Operation>, String> {
+    let mut result = Vec::new();
+    for (line_number, line) in text.lines().enumerate() {
+        let line_number = line_number + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let (keyword, rest) = line
+            .split_once(char::is_whitespace)
+            .ok_or(format!("Line {}: could not parse \"{}\".", line_number, line))?;
+        let (source_or_duplicate, target_or_original) =
+            rest.split_once("->").ok_or(format!(
+                "Line {}: expected \"<path> -> <path>\", got \"{}\".",
+                line_number, line
+            ))?;
+        let left = PathBuf::from(source_or_duplicate.trim());
+        let right = PathBuf::from(target_or_original.trim());
+
+        let operation = match keyword {
+            "copy" => Operation::Copy(Copy {
+                source: left,
+                target: right,
+            }),
+            "move" => Operation::Move(Move {
+                source: left,
+                target: right,
+            }),
+            "remove-duplicate" => Operation::RemoveDuplicate(RemoveDuplicate {
+                duplicate: left,
+                original: right,
+            }),
+            other => {
+                return Err(format!(
+                    "Line {}: unknown operation \"{}\".",
+                    line_number, other
+                ))
+            }
+        };
+        result.push(operation);
+    }
+    Ok(result)
+}
+
+/// Rejects an edited plan that would overwrite itself (two operations
+/// writing to the same target) or move/remove a file that is not actually
+/// there, reporting the offending operation.
+fn validate_operations(operations: &[Operation], target_directory: &Path) -> Result<(), String> {
+    let mut seen_targets: std::collections::BTreeSet<&PathBuf> = std::collections::BTreeSet::new();
+    for operation in operations {
+        match operation {
+            Operation::Copy(Copy { source, target }) => {
+                if !seen_targets.insert(target) {
+                    return Err(format!(
+                        "Duplicate target path \"{}\" in edited plan.",
+                        target.to_string_lossy()
+                    ));
+                }
+                if !source.exists() {
+                    return Err(format!(
+                        "Edited plan references a source \"{}\" which does not exist.",
+                        source.to_string_lossy()
+                    ));
+                }
+            }
+            Operation::Move(Move { source, target }) => {
+                if !seen_targets.insert(target) {
+                    return Err(format!(
+                        "Duplicate target path \"{}\" in edited plan.",
+                        target.to_string_lossy()
+                    ));
+                }
+                if !target_directory.join(source).exists() {
+                    return Err(format!(
+                        "Edited plan references a source \"{}\" which does not exist.",
+                        source.to_string_lossy()
+                    ));
+                }
+            }
+            Operation::RemoveDuplicate(RemoveDuplicate { duplicate, .. }) => {
+                if !target_directory.join(duplicate).exists() {
+                    return Err(format!(
+                        "Edited plan references a duplicate \"{}\" which does not exist.",
+                        duplicate.to_string_lossy()
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Opens the plan in `$EDITOR` (falling back to `vi`) and re-parses it once
+/// the user saves and exits, letting them delete or redirect operations
+/// before anything is executed.
+fn edit_operations(operations: Vec<Operation>) -> Result<Vec<Operation>, String> {
+    let editor = std::env::var("EDITOR").unwrap_or_else(|_| "vi".to_string());
+
+    let mut file = tempfile::NamedTempFile::new().or(Err(
+        "Failed to create a temporary file for the editor.".to_string(),
+    ))?;
+    file.write_all(format_operations(&operations).as_bytes())
+        .or(Err("Failed to write the plan to a temporary file.".to_string()))?;
+    file.flush()
+        .or(Err("Failed to flush the temporary file.".to_string()))?;
+
+    let status = std::process::Command::new(&editor)
+        .arg(file.path())
+        .status()
+        .or(Err(format!("Failed to launch editor \"{}\".", editor)))?;
+    if !status.success() {
+        return Err(format!("Editor \"{}\" exited with an error.", editor));
+    }
+
+    let edited = std::fs::read_to_string(file.path()).or(Err(
+        "Failed to read back the edited plan.".to_string(),
+    ))?;
+    parse_operations(&edited)
+}
+
 fn create_parent(full_target_path: &Path) -> Result<(), String> {
     let full_target_directory: &Path = full_target_path.parent().ok_or(format!(
         "Failed to get parent of path \"{}\".",
@@ -225,8 +719,12 @@ fn create_parent(full_target_path: &Path) -> Result<(), String> {
     Ok(())
 }
 
-fn execute_copy(source: &Path, target: &Path, operation: &Copy) -> Result<(), String> {
-    let full_source_path: PathBuf = source.join(&operation.source);
+/// Copies `operation.source` into the target directory. The copy itself is
+/// written to a temporary file next to the final path and only renamed into
+/// place once it is complete, so a process killed mid-copy never leaves a
+/// partial file sitting at the target path where `operation_already_done`
+/// would mistake it for a finished copy on `--resume`.
+fn execute_copy(target: &Path, operation: &Copy) -> Result<(), String> {
     let full_target_path: PathBuf = target.join(&operation.target);
 
     if full_target_path.exists() {
@@ -237,10 +735,31 @@ fn execute_copy(source: &Path, target: &Path, operation: &Copy) -> Result<(), St
     }
 
     create_parent(&full_target_path)?;
+    let full_target_directory = full_target_path.parent().ok_or(format!(
+        "Failed to get parent of path \"{}\".",
+        full_target_path.to_string_lossy()
+    ))?;
 
-    std::fs::copy(&full_source_path, &full_target_path).or(Err(format!(
+    let mut temp_file = tempfile::NamedTempFile::new_in(full_target_directory).or(Err(format!(
+        "Failed to create a temporary file in \"{}\" for the copy.",
+        full_target_directory.to_string_lossy()
+    )))?;
+    let mut source_file = std::fs::File::open(&operation.source).or(Err(format!(
+        "Failed to open \"{}\" for copying.",
+        operation.source.to_string_lossy()
+    )))?;
+    std::io::copy(&mut source_file, temp_file.as_file_mut()).or(Err(format!(
         "Copy from \"{}\" to \"{}\" failed.",
-        full_source_path.to_string_lossy(),
+        operation.source.to_string_lossy(),
+        full_target_path.to_string_lossy()
+    )))?;
+    temp_file.as_file().sync_all().or(Err(format!(
+        "Failed to fsync temporary file for copy to \"{}\".",
+        full_target_path.to_string_lossy()
+    )))?;
+
+    temp_file.persist(&full_target_path).or(Err(format!(
+        "Failed to finalize copy to \"{}\".",
         full_target_path.to_string_lossy()
     )))?;
 
@@ -268,26 +787,158 @@ fn execute_move(target: &Path, operation: &Move) -> Result<(), String> {
 }
 
 fn execute_remove_duplicate(target: &Path, operation: &RemoveDuplicate) -> Result<(), String> {
-    let full_target_path: PathBuf = target.join(&operation.duplicate);
-    std::fs::remove_file(&full_target_path).or(Err(format!(
+    let full_duplicate_path: PathBuf = target.join(&operation.duplicate);
+    let full_original_path: PathBuf = target.join(&operation.original);
+
+    // The plan was built from a snapshot of the directory; re-check right
+    // before deleting in case the files changed on disk in the meantime.
+    // A mismatch here is reported as a conflict instead of deleting a file
+    // which may no longer be a true duplicate.
+    let duplicate_hash = hash_file(&full_duplicate_path)?;
+    let original_hash = hash_file(&full_original_path)?;
+    if duplicate_hash != original_hash {
+        return Err(format!(
+            "Conflict: \"{}\" no longer matches \"{}\", refusing to remove it.",
+            full_duplicate_path.to_string_lossy(),
+            full_original_path.to_string_lossy()
+        ));
+    }
+
+    std::fs::remove_file(&full_duplicate_path).or(Err(format!(
         "Failed to remove \"{}\".",
-        full_target_path.to_string_lossy()
+        full_duplicate_path.to_string_lossy()
     )))
 }
 
-fn execute(source: &Path, target: &Path, script: &Vec<Operation>) -> Result<(), String> {
-    for operation in script.iter() {
-        match operation {
+/// Name of the journal file kept at the root of the target directory while
+/// a run is in progress, and removed again once it completes cleanly.
+const JOURNAL_FILE_NAME: &str = ".photo_sync_journal.json";
+
+#[derive(Serialize, Deserialize)]
+struct JournalState {
+    operations: Vec<Operation>,
+    complete: Vec<bool>,
+}
+
+/// On-disk record of an in-progress `execute` run, allowing it to be
+/// resumed after a crash instead of re-analyzing the directories. Each
+/// completed step is persisted (and fsynced) before moving on to the next.
+struct Journal {
+    path: PathBuf,
+    state: JournalState,
+}
+
+impl Journal {
+    fn persist(&self) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(&self.state)
+            .or(Err("Failed to serialize journal.".to_string()))?;
+        let mut file = std::fs::File::create(&self.path).or(Err(format!(
+            "Failed to create journal \"{}\".",
+            self.path.to_string_lossy()
+        )))?;
+        file.write_all(json.as_bytes()).or(Err(format!(
+            "Failed to write journal \"{}\".",
+            self.path.to_string_lossy()
+        )))?;
+        file.sync_all().or(Err(format!(
+            "Failed to fsync journal \"{}\".",
+            self.path.to_string_lossy()
+        )))?;
+        Ok(())
+    }
+
+    fn mark_complete(&mut self, step: usize) -> Result<(), String> {
+        self.state.complete[step] = true;
+        self.persist()
+    }
+}
+
+fn journal_path(target_directory: &Path) -> PathBuf {
+    target_directory.join(JOURNAL_FILE_NAME)
+}
+
+fn write_journal(target_directory: &Path, operations: Vec<Operation>) -> Result<Journal, String> {
+    let complete = vec![false; operations.len()];
+    let journal = Journal {
+        path: journal_path(target_directory),
+        state: JournalState {
+            operations,
+            complete,
+        },
+    };
+    journal.persist()?;
+    Ok(journal)
+}
+
+fn load_journal(target_directory: &Path) -> Result<Option<Journal>, String> {
+    let path = journal_path(target_directory);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents = std::fs::read_to_string(&path).or(Err(format!(
+        "Failed to read journal \"{}\".",
+        path.to_string_lossy()
+    )))?;
+    let state: JournalState = serde_json::from_str(&contents).or(Err(format!(
+        "Failed to parse journal \"{}\".",
+        path.to_string_lossy()
+    )))?;
+    Ok(Some(Journal { path, state }))
+}
+
+fn remove_journal(journal: &Journal) -> Result<(), String> {
+    std::fs::remove_file(&journal.path).or(Err(format!(
+        "Failed to remove journal \"{}\".",
+        journal.path.to_string_lossy()
+    )))
+}
+
+/// Whether `operation` has already taken effect on disk, so a resumed run
+/// can skip it even if the journal itself was not marked complete (e.g. the
+/// operation succeeded but the process died before the journal was synced).
+/// Only meaningful on a resumed run: on a fresh run a pre-existing file at
+/// the target path is a real conflict, not evidence the operation already
+/// ran, so callers must only consult this while resuming.
+fn operation_already_done(target: &Path, operation: &Operation) -> bool {
+    match operation {
+        Operation::Copy(op) => target.join(&op.target).exists(),
+        Operation::Move(op) => {
+            !target.join(&op.source).exists() && target.join(&op.target).exists()
+        }
+        Operation::RemoveDuplicate(op) => !target.join(&op.duplicate).exists(),
+    }
+}
+
+/// Runs `journal`'s operations, skipping any already marked complete. When
+/// `resuming` is set, a step already visible on disk (per
+/// `operation_already_done`) is accepted as done instead of being replayed;
+/// on a fresh run every step must actually execute, so a pre-existing file
+/// at a target path is left to `execute_copy`/`execute_move`'s own
+/// overwrite guard instead of being mistaken for prior progress.
+fn execute(target: &Path, journal: &mut Journal, resuming: bool) -> Result<(), String> {
+    for step in 0..journal.state.operations.len() {
+        if journal.state.complete[step] {
+            continue;
+        }
+
+        let operation = journal.state.operations[step].clone();
+        if resuming && operation_already_done(target, &operation) {
+            journal.mark_complete(step)?;
+            continue;
+        }
+
+        match &operation {
             Operation::Copy(operation) => {
-                execute_copy(source, target, &operation)?;
+                execute_copy(target, operation)?;
             }
             Operation::Move(operation) => {
-                execute_move(target, &operation)?;
+                execute_move(target, operation)?;
             }
             Operation::RemoveDuplicate(operation) => {
-                execute_remove_duplicate(target, &operation)?;
+                execute_remove_duplicate(target, operation)?;
             }
         }
+        journal.mark_complete(step)?;
     }
 
     Ok(())
@@ -295,35 +946,80 @@ fn execute(source: &Path, target: &Path, script: &Vec<Operation>) -> Result<(),
 
 fn main2() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
+
+    if args.resume {
+        let target_directory = resolve_target_directory(args.paths, args.target_directory)?;
+        println!("Resuming into {}.", target_directory.to_string_lossy());
+        let mut journal = load_journal(&target_directory)?
+            .ok_or("No journal found in the target directory to resume from.")?;
+        println!(
+            "Resuming {} previously planned operation(s).",
+            journal.state.operations.len()
+        );
+        execute(&target_directory, &mut journal, true)?;
+        remove_journal(&journal)?;
+        return Ok(());
+    }
+
+    let (source_directories, target_directory) =
+        resolve_directories(args.paths, args.target_directory)?;
+
     println!(
-        "Synchronize photo collection from {} to {}.",
-        args.source_directory.to_string_lossy(),
-        args.target_directory.to_string_lossy()
+        "Synchronize photo collection(s) [{}] to {}.",
+        source_directories
+            .iter()
+            .map(|path| path.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(", "),
+        target_directory.to_string_lossy()
     );
     println!("Dry run: {}", args.dry_run);
 
-    let analyzed_source = analyze_directory(&args.source_directory)?;
+    let jobs = args
+        .jobs
+        .or_else(|| std::thread::available_parallelism().ok().map(|n| n.get()))
+        .unwrap_or(1);
+    let filters = Filters::compile(&args.include, &args.exclude)?;
+
+    let mut per_source = Vec::new();
+    for source_directory in &source_directories {
+        per_source.push(analyze_directory(
+            source_directory,
+            args.verify,
+            jobs,
+            &filters,
+        )?);
+    }
+    let analyzed_source = merge_sources(per_source)?;
+
     let duplicates_in_source = get_duplicates(&analyzed_source)?;
     if !duplicates_in_source.is_empty() {
         display_duplicates(&duplicates_in_source);
         return Err("The source has duplicates".into());
     }
 
-    let analyzed_target = analyze_directory(&args.target_directory)?;
+    let analyzed_target = analyze_directory(&target_directory, args.verify, jobs, &filters)?;
 
     println!("Analyzed source:");
     display_analyzed_directory(&analyzed_source);
     println!("Analyzed target:");
     display_analyzed_directory(&analyzed_target);
 
-    let operations = sync(&analyzed_source, &analyzed_target)?;
+    let mut operations = sync(&analyzed_source, &analyzed_target)?;
+
+    if args.interactive {
+        operations = edit_operations(operations)?;
+        validate_operations(&operations, &target_directory)?;
+    }
 
     for operation in operations.iter() {
         print_operation(operation);
     }
 
     if !args.dry_run {
-        execute(&args.source_directory, &args.target_directory, &operations)?;
+        let mut journal = write_journal(&target_directory, operations)?;
+        execute(&target_directory, &mut journal, false)?;
+        remove_journal(&journal)?;
     }
 
     Ok(())
@@ -339,3 +1035,246 @@ fn main() -> ExitCode {
         Ok(()) => ExitCode::SUCCESS,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copy_is_done_once_target_exists() {
+        let target = tempfile::tempdir().unwrap();
+        let operation = Operation::Copy(Copy {
+            source: PathBuf::from("/does/not/matter.jpg"),
+            target: PathBuf::from("photo.jpg"),
+        });
+        assert!(!operation_already_done(target.path(), &operation));
+
+        std::fs::write(target.path().join("photo.jpg"), b"anything").unwrap();
+        assert!(operation_already_done(target.path(), &operation));
+    }
+
+    #[test]
+    fn move_is_done_once_source_gone_and_target_present() {
+        let target = tempfile::tempdir().unwrap();
+        let operation = Operation::Move(Move {
+            source: PathBuf::from("old.jpg"),
+            target: PathBuf::from("new.jpg"),
+        });
+        std::fs::write(target.path().join("old.jpg"), b"content").unwrap();
+        assert!(!operation_already_done(target.path(), &operation));
+
+        std::fs::rename(
+            target.path().join("old.jpg"),
+            target.path().join("new.jpg"),
+        )
+        .unwrap();
+        assert!(operation_already_done(target.path(), &operation));
+    }
+
+    #[test]
+    fn remove_duplicate_is_done_once_duplicate_gone() {
+        let target = tempfile::tempdir().unwrap();
+        let operation = Operation::RemoveDuplicate(RemoveDuplicate {
+            duplicate: PathBuf::from("dup.jpg"),
+            original: PathBuf::from("original.jpg"),
+        });
+        std::fs::write(target.path().join("dup.jpg"), b"content").unwrap();
+        assert!(!operation_already_done(target.path(), &operation));
+
+        std::fs::remove_file(target.path().join("dup.jpg")).unwrap();
+        assert!(operation_already_done(target.path(), &operation));
+    }
+
+    #[test]
+    fn format_and_parse_operations_roundtrip() {
+        let operations = vec![
+            Operation::Copy(Copy {
+                source: PathBuf::from("/source/a.jpg"),
+                target: PathBuf::from("a.jpg"),
+            }),
+            Operation::Move(Move {
+                source: PathBuf::from("old/b.jpg"),
+                target: PathBuf::from("new/b.jpg"),
+            }),
+            Operation::RemoveDuplicate(RemoveDuplicate {
+                duplicate: PathBuf::from("c (copy).jpg"),
+                original: PathBuf::from("c.jpg"),
+            }),
+        ];
+
+        let formatted = format_operations(&operations);
+        let parsed = parse_operations(&formatted).unwrap();
+        assert_eq!(parsed, operations);
+    }
+
+    #[test]
+    fn parse_operations_ignores_blank_lines() {
+        let parsed = parse_operations("\ncopy a -> b\n\n").unwrap();
+        assert_eq!(
+            parsed,
+            vec![Operation::Copy(Copy {
+                source: PathBuf::from("a"),
+                target: PathBuf::from("b"),
+            })]
+        );
+    }
+
+    #[test]
+    fn parse_operations_rejects_unknown_keyword() {
+        assert!(parse_operations("teleport a -> b").is_err());
+    }
+
+    #[test]
+    fn parse_operations_rejects_missing_arrow() {
+        assert!(parse_operations("copy a b").is_err());
+    }
+
+    #[test]
+    fn validate_operations_rejects_duplicate_targets() {
+        let target = tempfile::tempdir().unwrap();
+        let operations = vec![
+            Operation::Copy(Copy {
+                source: target.path().join("a.jpg"),
+                target: PathBuf::from("out.jpg"),
+            }),
+            Operation::Move(Move {
+                source: PathBuf::from("b.jpg"),
+                target: PathBuf::from("out.jpg"),
+            }),
+        ];
+        std::fs::write(target.path().join("a.jpg"), b"content").unwrap();
+        std::fs::write(target.path().join("b.jpg"), b"content").unwrap();
+
+        assert!(validate_operations(&operations, target.path()).is_err());
+    }
+
+    #[test]
+    fn validate_operations_rejects_missing_source() {
+        let target = tempfile::tempdir().unwrap();
+        let operations = vec![Operation::Copy(Copy {
+            source: target.path().join("missing.jpg"),
+            target: PathBuf::from("out.jpg"),
+        })];
+
+        assert!(validate_operations(&operations, target.path()).is_err());
+    }
+
+    #[test]
+    fn validate_operations_accepts_a_consistent_plan() {
+        let target = tempfile::tempdir().unwrap();
+        std::fs::write(target.path().join("a.jpg"), b"content").unwrap();
+        let operations = vec![Operation::Copy(Copy {
+            source: target.path().join("a.jpg"),
+            target: PathBuf::from("out.jpg"),
+        })];
+
+        assert!(validate_operations(&operations, target.path()).is_ok());
+    }
+
+    fn analyze_one(root: &Path, relative_path: &str, content: &[u8]) -> AnalyzedDirectory {
+        std::fs::write(root.join(relative_path), content).unwrap();
+        analyze_directory(root, false, 1, &Filters::compile(&[], &[]).unwrap()).unwrap()
+    }
+
+    fn analyze_all(root: &Path, files: &[(&str, &[u8])]) -> AnalyzedDirectory {
+        for (relative_path, content) in files {
+            let full_path = root.join(relative_path);
+            create_parent(&full_path).unwrap();
+            std::fs::write(full_path, content).unwrap();
+        }
+        analyze_directory(root, false, 1, &Filters::compile(&[], &[]).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn sync_points_remove_duplicate_at_the_moved_file_final_location() {
+        let source = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        let analyzed_source = analyze_all(source.path(), &[("new/photo.jpg", b"content")]);
+        let analyzed_target = analyze_all(
+            target.path(),
+            &[("a/photo.jpg", b"content"), ("b/photo.jpg", b"content")],
+        );
+
+        let operations = sync(&analyzed_source, &analyzed_target).unwrap();
+
+        let move_op = operations
+            .iter()
+            .find_map(|op| match op {
+                Operation::Move(m) => Some(m),
+                _ => None,
+            })
+            .expect("expected a Move operation");
+        let remove_op = operations
+            .iter()
+            .find_map(|op| match op {
+                Operation::RemoveDuplicate(r) => Some(r),
+                _ => None,
+            })
+            .expect("expected a RemoveDuplicate operation");
+
+        assert_eq!(remove_op.original, move_op.target);
+    }
+
+    #[test]
+    fn merge_sources_dedupes_identical_content_across_roots() {
+        let source_a = tempfile::tempdir().unwrap();
+        let source_b = tempfile::tempdir().unwrap();
+        let analyzed_a = analyze_one(source_a.path(), "pic.jpg", b"SAME-CONTENT");
+        let analyzed_b = analyze_one(source_b.path(), "pic.jpg", b"SAME-CONTENT");
+
+        let merged = merge_sources(vec![analyzed_a, analyzed_b]).unwrap();
+
+        let candidates: Vec<_> = merged.map.values().flatten().collect();
+        assert_eq!(candidates.len(), 1);
+    }
+
+    #[test]
+    fn merge_sources_rejects_conflicting_content_across_roots() {
+        let source_a = tempfile::tempdir().unwrap();
+        let source_b = tempfile::tempdir().unwrap();
+        let analyzed_a = analyze_one(source_a.path(), "pic.jpg", b"AAAA");
+        let analyzed_b = analyze_one(source_b.path(), "pic.jpg", b"BBBB");
+
+        assert!(merge_sources(vec![analyzed_a, analyzed_b]).is_err());
+    }
+
+    #[test]
+    fn merge_sources_rejects_conflicting_content_of_different_sizes() {
+        let source_a = tempfile::tempdir().unwrap();
+        let source_b = tempfile::tempdir().unwrap();
+        let analyzed_a = analyze_all(source_a.path(), &[("trip/img1.jpg", b"AAAA")]);
+        let analyzed_b = analyze_all(source_b.path(), &[("trip/img1.jpg", b"BBBBBBBBBB")]);
+
+        assert!(merge_sources(vec![analyzed_a, analyzed_b]).is_err());
+    }
+
+    #[test]
+    fn filters_include_only_keeps_matching_paths() {
+        let filters = Filters::compile(&["*.jpg".to_string()], &[]).unwrap();
+        assert!(filters.matches(Path::new("a.jpg")));
+        assert!(!filters.matches(Path::new("a.png")));
+    }
+
+    #[test]
+    fn filters_exclude_only_drops_matching_paths() {
+        let filters = Filters::compile(&[], &["*.png".to_string()]).unwrap();
+        assert!(filters.matches(Path::new("a.jpg")));
+        assert!(!filters.matches(Path::new("a.png")));
+    }
+
+    #[test]
+    fn filters_exclude_overrides_include() {
+        let filters =
+            Filters::compile(&["*.jpg".to_string()], &["private*.jpg".to_string()]).unwrap();
+        assert!(filters.matches(Path::new("a.jpg")));
+        assert!(!filters.matches(Path::new("private_a.jpg")));
+    }
+
+    #[test]
+    fn filters_repeated_include_patterns_are_all_considered() {
+        let filters = Filters::compile(&["*.jpg".to_string(), "*.png".to_string()], &[]).unwrap();
+        assert!(filters.matches(Path::new("a.jpg")));
+        assert!(filters.matches(Path::new("a.png")));
+        assert!(!filters.matches(Path::new("a.gif")));
+    }
+}